@@ -1,24 +1,73 @@
 use clap::Parser;
-use shunting_yard::{tokenize, evaluate};
+use shunting_yard::{evaluate_with, tokenize, Environment, Numeric, Rational, Value};
+
+#[derive(Copy, Clone, Debug, clap::ValueEnum)]
+enum NumberBackend {
+    Float,
+    Rational,
+}
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// The mathematical expression to evaluate
+    /// The expression to evaluate. Multiple statements may be separated by
+    /// `;`, sharing the same variable environment.
+    #[arg(allow_hyphen_values = true)]
     expression: String,
+
+    /// Bind a variable before evaluating, e.g. `--set x=3`. May be repeated.
+    #[arg(long = "set", value_name = "NAME=VALUE")]
+    set: Vec<String>,
+
+    /// Which number representation to evaluate with: `float` (the default,
+    /// inexact `f64`) or `rational` (exact numerator/denominator pairs).
+    #[arg(long = "numbers", value_enum, default_value = "float")]
+    numbers: NumberBackend,
 }
 
 
 fn main() {
     let args = Args::parse();
-    
-    match calculate(&args.expression) {
+
+    let result = match args.numbers {
+        NumberBackend::Float => run::<f64>(&args),
+        NumberBackend::Rational => run::<Rational>(&args),
+    };
+
+    match result {
         Ok(result) => println!("Result: {}", result),
         Err(err) => println!("Error: {}", err),
     }
 }
 
-fn calculate(expression: &str) -> Result<f32, String> {
-    let tokens = tokenize(expression);
-    evaluate(tokens).map_err(|e| e.to_string())
+fn run<N: Numeric>(args: &Args) -> Result<String, String> {
+    let mut env = Environment::<N>::new();
+
+    for binding in &args.set {
+        apply_binding(binding, &mut env)?;
+    }
+
+    calculate(&args.expression, &mut env).map(|v| v.to_string())
+}
+
+fn apply_binding<N: Numeric>(binding: &str, env: &mut Environment<N>) -> Result<(), String> {
+    let (name, value) = binding
+        .split_once('=')
+        .ok_or_else(|| format!("Invalid --set binding: {}", binding))?;
+    let value = evaluate_with(tokenize(value), env).map_err(|e| e.to_string())?;
+    env.insert(name.to_string(), value);
+    Ok(())
+}
+
+fn calculate<N: Numeric>(expression: &str, env: &mut Environment<N>) -> Result<Value<N>, String> {
+    let mut result =
+        Value::Number(N::from_str("0").expect("\"0\" is always a valid numeric literal"));
+    for statement in expression.split(';') {
+        let statement = statement.trim();
+        if statement.is_empty() {
+            continue;
+        }
+        result = evaluate_with(tokenize(statement), env).map_err(|e| e.to_string())?;
+    }
+    Ok(result)
 }
@@ -1,5 +1,7 @@
 use core::fmt;
-use std::f64::NAN;
+use std::collections::HashMap;
+
+use num_bigint::BigInt;
 
 #[derive(Copy, Clone, Debug)]
 pub enum Operator {
@@ -12,22 +14,454 @@ pub enum Operator {
     Sub,
     Mod,
     Exp,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Eq,
+    Ne,
+    And,
+    Or,
+    Assign,
+    /// Unary `+`, e.g. the leading `+` in `+3`. A no-op on numbers, kept
+    /// distinct from `Add` so the shunting yard can give it its own
+    /// (higher, right-associative) precedence.
+    Pos,
+    /// Unary `-`, e.g. the leading `-` in `-3` or `2 * -3`.
+    Neg,
+}
+
+/// Whether repeated same-precedence operators group from the left
+/// (`a-b-c == (a-b)-c`) or the right (`a^b^c == a^(b^c)`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Associativity {
+    Left,
+    Right,
+}
+
+/// Named values that persist across `evaluate_with` calls, e.g. across the
+/// statements of a `;`-separated input or repeated `--set` flags.
+pub type Environment<N> = HashMap<String, Value<N>>;
+
+/// A value produced by evaluating an expression: either a number in whatever
+/// backend `N` represents it with, or a boolean coming from comparison and
+/// logical operators.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value<N: Numeric> {
+    Number(N),
+    Bool(bool),
+}
+
+impl<N: Numeric> fmt::Display for Value<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(n) => write!(f, "{}", n),
+            Value::Bool(b) => write!(f, "{}", b),
+        }
+    }
+}
+
+/// A number backend that `evaluate`/`evaluate_with` can run against. `f64`
+/// is the original inexact backend; `Rational` is an exact alternative that
+/// parses decimal literals into reduced fractions instead of floats.
+pub trait Numeric: Clone + fmt::Debug + fmt::Display + PartialEq + PartialOrd {
+    /// Parse a decimal literal like `"3.5"` into this backend's
+    /// representation. Returns `None` on malformed input.
+    fn from_str(s: &str) -> Option<Self>;
+    fn add(&self, other: &Self) -> Self;
+    fn sub(&self, other: &Self) -> Self;
+    fn mul(&self, other: &Self) -> Self;
+    fn div(&self, other: &Self) -> Self;
+    fn rem(&self, other: &Self) -> Self;
+    fn pow(&self, other: &Self) -> Self;
+    /// Whether this value is an invalid/unrepresentable result, e.g. a
+    /// float `NaN` or a rational with a zero denominator.
+    fn is_nan(&self) -> bool;
+
+    /// The constant `pi`, for the bare `pi` identifier.
+    fn pi() -> Self;
+    /// The constant `e`, for the bare `e` identifier.
+    fn e() -> Self;
+    fn sin(&self) -> Self;
+    fn cos(&self) -> Self;
+    fn tan(&self) -> Self;
+    fn ln(&self) -> Self;
+    fn floor(&self) -> Self;
+    fn ceil(&self) -> Self;
+}
+
+impl Numeric for f64 {
+    fn from_str(s: &str) -> Option<Self> {
+        s.parse::<f64>().ok()
+    }
+    fn add(&self, other: &Self) -> Self {
+        self + other
+    }
+    fn sub(&self, other: &Self) -> Self {
+        self - other
+    }
+    fn mul(&self, other: &Self) -> Self {
+        self * other
+    }
+    fn div(&self, other: &Self) -> Self {
+        self / other
+    }
+    fn rem(&self, other: &Self) -> Self {
+        self % other
+    }
+    fn pow(&self, other: &Self) -> Self {
+        self.powf(*other)
+    }
+    fn is_nan(&self) -> bool {
+        f64::is_nan(*self)
+    }
+
+    fn pi() -> Self {
+        std::f64::consts::PI
+    }
+    fn e() -> Self {
+        std::f64::consts::E
+    }
+    fn sin(&self) -> Self {
+        f64::sin(*self)
+    }
+    fn cos(&self) -> Self {
+        f64::cos(*self)
+    }
+    fn tan(&self) -> Self {
+        f64::tan(*self)
+    }
+    fn ln(&self) -> Self {
+        f64::ln(*self)
+    }
+    fn floor(&self) -> Self {
+        f64::floor(*self)
+    }
+    fn ceil(&self) -> Self {
+        f64::ceil(*self)
+    }
+}
+
+/// An exact number backend: a reduced numerator/denominator pair over
+/// arbitrary-precision integers, so `0.1 + 0.2` and `10 % 3` come out exact
+/// instead of inheriting `f64`'s rounding.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rational {
+    numer: BigInt,
+    denom: BigInt,
+}
+
+impl Rational {
+    /// Build a rational from a numerator/denominator pair, reducing it via
+    /// gcd and normalizing the sign onto the numerator. A zero denominator
+    /// is kept as-is and reported through `Numeric::is_nan`.
+    fn new(numer: BigInt, denom: BigInt) -> Self {
+        let mut r = Rational { numer, denom };
+        r.reduce();
+        r
+    }
+
+    fn reduce(&mut self) {
+        if self.denom == BigInt::from(0) {
+            return;
+        }
+        if self.denom < BigInt::from(0) {
+            self.numer = -self.numer.clone();
+            self.denom = -self.denom.clone();
+        }
+        let g = gcd(abs_bigint(&self.numer), self.denom.clone());
+        if g > BigInt::from(1) {
+            self.numer = &self.numer / &g;
+            self.denom = &self.denom / &g;
+        }
+    }
+
+    fn to_f64(&self) -> f64 {
+        bigint_to_f64(&self.numer) / bigint_to_f64(&self.denom)
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.denom == BigInt::from(1) {
+            write!(f, "{}", self.numer)
+        } else {
+            write!(f, "{}/{}", self.numer, self.denom)
+        }
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        // Denominators are always positive post-reduction, so cross
+        // multiplication preserves ordering.
+        (&self.numer * &other.denom).partial_cmp(&(&other.numer * &self.denom))
+    }
+}
+
+impl Numeric for Rational {
+    fn from_str(s: &str) -> Option<Self> {
+        match s.split_once('.') {
+            Some((whole, frac)) => {
+                let negative = whole.starts_with('-');
+                let whole_digits = whole.trim_start_matches('-');
+                // A leading-dot literal like ".5" has an empty integer
+                // part, which `BigInt`'s parser rejects; treat it as 0, the
+                // same as the float backend's `".5".parse::<f64>()`.
+                let whole_part: BigInt = if whole_digits.is_empty() {
+                    BigInt::from(0)
+                } else {
+                    whole_digits.parse().ok()?
+                };
+                let frac_part: BigInt = frac.parse().ok()?;
+                let denom = big_pow(&BigInt::from(10), frac.len() as u32);
+                let magnitude = &whole_part * &denom + &frac_part;
+                let numer = if negative { -magnitude } else { magnitude };
+                Some(Rational::new(numer, denom))
+            }
+            None => {
+                let numer: BigInt = s.parse().ok()?;
+                Some(Rational::new(numer, BigInt::from(1)))
+            }
+        }
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        Rational::new(
+            &self.numer * &other.denom + &other.numer * &self.denom,
+            &self.denom * &other.denom,
+        )
+    }
+
+    fn sub(&self, other: &Self) -> Self {
+        Rational::new(
+            &self.numer * &other.denom - &other.numer * &self.denom,
+            &self.denom * &other.denom,
+        )
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        Rational::new(&self.numer * &other.numer, &self.denom * &other.denom)
+    }
+
+    fn div(&self, other: &Self) -> Self {
+        Rational::new(&self.numer * &other.denom, &self.denom * &other.numer)
+    }
+
+    fn rem(&self, other: &Self) -> Self {
+        // a % b = a - b * trunc(a / b), matching f64's truncating remainder;
+        // BigInt division already truncates toward zero.
+        let quotient = Rational::new(
+            (&self.numer * &other.denom) / (&self.denom * &other.numer),
+            BigInt::from(1),
+        );
+        self.sub(&quotient.mul(other))
+    }
+
+    fn pow(&self, other: &Self) -> Self {
+        if other.denom == BigInt::from(1) && other.numer >= BigInt::from(0) {
+            if let Some(exp) = bigint_to_u32(&other.numer) {
+                return Rational::new(big_pow(&self.numer, exp), big_pow(&self.denom, exp));
+            }
+        }
+        // Negative or fractional exponents generally have no exact rational
+        // result (e.g. sqrt(2) is irrational), so fall back to floating
+        // point and re-parse the decimal rendering back into a rational.
+        // This is the same edge case OpenTally's `pow_assign` leaves as a
+        // TODO for negative exponents.
+        rational_from_f64(self.to_f64().powf(other.to_f64()))
+    }
+
+    fn is_nan(&self) -> bool {
+        self.denom == BigInt::from(0)
+    }
+
+    fn pi() -> Self {
+        Rational::from_str("3.14159265358979").expect("constant literal is always valid")
+    }
+    fn e() -> Self {
+        Rational::from_str("2.71828182845905").expect("constant literal is always valid")
+    }
+
+    // `sin`/`cos`/`tan`/`ln` have no exact rational result in general (e.g.
+    // `sin(1)` is transcendental), so like `pow`'s negative-exponent case
+    // these fall back to a float-precision decimal approximation.
+    fn sin(&self) -> Self {
+        rational_from_f64(self.to_f64().sin())
+    }
+    fn cos(&self) -> Self {
+        rational_from_f64(self.to_f64().cos())
+    }
+    fn tan(&self) -> Self {
+        rational_from_f64(self.to_f64().tan())
+    }
+    fn ln(&self) -> Self {
+        rational_from_f64(self.to_f64().ln())
+    }
+
+    // Unlike the above, flooring/ceiling a rational has an exact answer, so
+    // it's computed directly on the numerator/denominator pair.
+    fn floor(&self) -> Self {
+        if self.denom == BigInt::from(0) {
+            return self.clone();
+        }
+        let q = &self.numer / &self.denom;
+        let r = &self.numer % &self.denom;
+        let q = if r != BigInt::from(0) && self.numer < BigInt::from(0) {
+            q - BigInt::from(1)
+        } else {
+            q
+        };
+        Rational::new(q, BigInt::from(1))
+    }
+    fn ceil(&self) -> Self {
+        if self.denom == BigInt::from(0) {
+            return self.clone();
+        }
+        let q = &self.numer / &self.denom;
+        let r = &self.numer % &self.denom;
+        let q = if r != BigInt::from(0) && self.numer > BigInt::from(0) {
+            q + BigInt::from(1)
+        } else {
+            q
+        };
+        Rational::new(q, BigInt::from(1))
+    }
+}
+
+/// Render an inexact float result as a decimal string and re-parse it into a
+/// rational, for the handful of `Rational` operations (fractional `pow`,
+/// `sin`/`cos`/`tan`/`ln`) with no exact rational result.
+fn rational_from_f64(x: f64) -> Rational {
+    Rational::from_str(&format!("{:.12}", x))
+        .unwrap_or_else(|| Rational::new(BigInt::from(0), BigInt::from(0)))
+}
+
+fn abs_bigint(n: &BigInt) -> BigInt {
+    if *n < BigInt::from(0) {
+        -n.clone()
+    } else {
+        n.clone()
+    }
+}
+
+fn bigint_to_u32(n: &BigInt) -> Option<u32> {
+    n.to_string().parse().ok()
+}
+
+fn bigint_to_f64(n: &BigInt) -> f64 {
+    n.to_string().parse().unwrap_or(f64::NAN)
+}
+
+fn big_pow(base: &BigInt, mut exp: u32) -> BigInt {
+    let mut result = BigInt::from(1);
+    let mut base = base.clone();
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = &result * &base;
+        }
+        base = &base * &base;
+        exp >>= 1;
+    }
+    result
+}
+
+fn gcd(mut a: BigInt, mut b: BigInt) -> BigInt {
+    while b != BigInt::from(0) {
+        let r = &a % &b;
+        a = b;
+        b = r;
+    }
+    a
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct OperatorConversionError;
+
+/// The specific cause of an evaluation failure, independent of where in the
+/// expression it happened (see `EvalError::pos` for that).
 #[derive(Debug, Clone, PartialEq)]
-pub struct PostfixEvalError;
+pub enum EvalErrorKind {
+    /// An operand looked like a numeric literal (it started with a digit or
+    /// `.`) but didn't parse as one, e.g. `"3.4.5"`.
+    InvalidNumber(String),
+    /// An operator didn't have enough operands on the stack.
+    MissingOperand,
+    /// More than one operand remained on the stack after evaluation; the
+    /// count is how many are left over.
+    TrailingOperands(usize),
+    DivisionByZero,
+    /// An operator was applied to operands of the wrong `Value` variant,
+    /// e.g. adding two `Bool`s.
+    TypeMismatch,
+    UndefinedVariable(String),
+    /// The left-hand side of `=` wasn't a bare variable name.
+    InvalidAssignmentTarget,
+    /// An operation produced a backend-reported invalid result (e.g. a
+    /// fractional power of a negative number) that isn't a division by zero.
+    InvalidOperation,
+    /// A token stream that shouldn't be reachable from `tokenize` (e.g. an
+    /// unmatched parenthesis surviving into the postfix stream).
+    MalformedExpression,
+    /// A `Token::Function` whose name isn't one of the builtins `evaluate`
+    /// dispatches.
+    UnknownFunction(String),
+    /// A builtin function call with the wrong number of arguments, e.g.
+    /// `log(8)` (missing the base) or `sqrt(2, 3)`.
+    WrongArgumentCount {
+        function: String,
+        expected: usize,
+        got: usize,
+    },
+}
 
-impl fmt::Display for OperatorConversionError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Invalid character for operator")
+impl fmt::Display for EvalErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalErrorKind::InvalidNumber(text) => write!(f, "invalid number literal '{}'", text),
+            EvalErrorKind::MissingOperand => write!(f, "missing operand"),
+            EvalErrorKind::TrailingOperands(n) => {
+                write!(f, "{} operand(s) left over after evaluation", n)
+            }
+            EvalErrorKind::DivisionByZero => write!(f, "division by zero"),
+            EvalErrorKind::TypeMismatch => write!(f, "type mismatch"),
+            EvalErrorKind::UndefinedVariable(name) => write!(f, "undefined variable '{}'", name),
+            EvalErrorKind::InvalidAssignmentTarget => {
+                write!(f, "left-hand side of '=' must be a variable name")
+            }
+            EvalErrorKind::InvalidOperation => write!(f, "invalid operation"),
+            EvalErrorKind::MalformedExpression => write!(f, "malformed expression"),
+            EvalErrorKind::UnknownFunction(name) => write!(f, "unknown function '{}'", name),
+            EvalErrorKind::WrongArgumentCount {
+                function,
+                expected,
+                got,
+            } => write!(
+                f,
+                "'{}' expects {} argument(s), got {}",
+                function, expected, got
+            ),
+        }
+    }
+}
+
+/// An evaluation failure, carrying both its cause and the character index
+/// into the (whitespace-stripped) expression where it occurred.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvalError {
+    pub kind: EvalErrorKind,
+    pub pos: usize,
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at position {})", self.kind, self.pos)
     }
 }
 
-impl fmt::Display for PostfixEvalError {
+impl fmt::Display for OperatorConversionError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Invalid postfix expression")
+        write!(f, "Invalid character for operator")
     }
 }
 
@@ -44,6 +478,9 @@ impl TryFrom<char> for Operator {
             '(' => Ok(Operator::OpenParen),
             '^' => Ok(Operator::Pow),
             '-' => Ok(Operator::Sub),
+            '<' => Ok(Operator::Lt),
+            '>' => Ok(Operator::Gt),
+            '=' => Ok(Operator::Assign),
             _ => Err(OperatorConversionError),
         };
 
@@ -51,18 +488,57 @@ impl TryFrom<char> for Operator {
     }
 }
 
-impl From<Operator> for char {
-    fn from(value: Operator) -> Self {
-        match value {
-            Operator::Add => '+',
-            Operator::CloseParen => ')',
-            Operator::Div => '/',
-            Operator::Exp => 'E',
-            Operator::Mod => '%',
-            Operator::Mult => '*',
-            Operator::OpenParen => '(',
-            Operator::Pow => '^',
-            Operator::Sub => '-',
+impl Operator {
+    /// The textual symbol for this operator, as it would appear in source.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Operator::Add => "+",
+            Operator::CloseParen => ")",
+            Operator::Div => "/",
+            Operator::Exp => "E",
+            Operator::Mod => "%",
+            Operator::Mult => "*",
+            Operator::OpenParen => "(",
+            Operator::Pow => "^",
+            Operator::Sub => "-",
+            Operator::Lt => "<",
+            Operator::Gt => ">",
+            Operator::Le => "<=",
+            Operator::Ge => ">=",
+            Operator::Eq => "==",
+            Operator::Ne => "!=",
+            Operator::And => "&&",
+            Operator::Or => "||",
+            Operator::Assign => "=",
+            Operator::Pos => "+",
+            Operator::Neg => "-",
+        }
+    }
+
+    /// Whether this operator groups left-to-right or right-to-left when
+    /// chained with itself at the same precedence; used by `tokenize`'s pop
+    /// condition.
+    fn associativity(&self) -> Associativity {
+        match self {
+            Operator::Assign | Operator::Pow | Operator::Pos | Operator::Neg => {
+                Associativity::Right
+            }
+            _ => Associativity::Left,
+        }
+    }
+
+    /// Two-character operators that single-character lexing can't see on
+    /// its own; checked against a lookahead pair before falling back to
+    /// `TryFrom<char>`.
+    fn try_from_pair(a: char, b: char) -> Option<Operator> {
+        match (a, b) {
+            ('<', '=') => Some(Operator::Le),
+            ('>', '=') => Some(Operator::Ge),
+            ('=', '=') => Some(Operator::Eq),
+            ('!', '=') => Some(Operator::Ne),
+            ('&', '&') => Some(Operator::And),
+            ('|', '|') => Some(Operator::Or),
+            _ => None,
         }
     }
 }
@@ -97,78 +573,371 @@ impl std::cmp::Ord for Operator {
     }
 }
 
+/// Wrap an arithmetic result, turning a backend-reported `NaN` (float
+/// `NaN`, or a rational with a zero denominator from e.g. division by zero)
+/// into `on_nan` instead of propagating a silently invalid value.
+fn checked<N: Numeric>(result: N, on_nan: EvalErrorKind) -> Result<Value<N>, EvalErrorKind> {
+    if result.is_nan() {
+        Err(on_nan)
+    } else {
+        Ok(Value::Number(result))
+    }
+}
+
 impl Operator {
     fn precidence(&self) -> u8 {
         match self {
-            Operator::Add => 1,
-            Operator::Div => 2,
-            Operator::Mod => 2,
-            Operator::Mult => 2,
-            Operator::Exp => 2,
-            Operator::Pow => 3,
-            Operator::Sub => 1,
+            Operator::Assign => 1,
+            Operator::Or => 2,
+            Operator::And => 3,
+            Operator::Lt | Operator::Gt | Operator::Le | Operator::Ge | Operator::Eq | Operator::Ne => 4,
+            Operator::Add => 5,
+            Operator::Sub => 5,
+            Operator::Div => 6,
+            Operator::Mod => 6,
+            Operator::Mult => 6,
+            Operator::Exp => 6,
+            // Unary `+`/`-` bind tighter than the binary arithmetic ops but
+            // looser than `^`, so `-3^2` parses as `-(3^2)` rather than
+            // `(-3)^2` — the conventional mathematical reading.
+            Operator::Pos | Operator::Neg => 7,
+            Operator::Pow => 8,
             _ => 0,
         }
     }
 
-    fn operate(&self, a: f64, b: f64) -> f64 {
+    fn operate<N: Numeric>(&self, a: Value<N>, b: Value<N>) -> Result<Value<N>, EvalErrorKind> {
+        use Value::*;
         match self {
-            Operator::Add => a + b,
-            Operator::Div => a / b,
-            Operator::Mod => a % b,
-            Operator::Mult => a * b,
-            Operator::Exp => a * 10.0_f64.powf(b),
-            Operator::Pow => a.powf(b),
-            Operator::Sub => a - b,
-            _ => NAN,
+            Operator::Add => match (a, b) {
+                (Number(x), Number(y)) => checked(x.add(&y), EvalErrorKind::InvalidOperation),
+                _ => Err(EvalErrorKind::TypeMismatch),
+            },
+            Operator::Sub => match (a, b) {
+                (Number(x), Number(y)) => checked(x.sub(&y), EvalErrorKind::InvalidOperation),
+                _ => Err(EvalErrorKind::TypeMismatch),
+            },
+            Operator::Div => match (a, b) {
+                (Number(x), Number(y)) => {
+                    let zero = N::from_str("0").expect("\"0\" is always a valid numeric literal");
+                    if y == zero {
+                        return Err(EvalErrorKind::DivisionByZero);
+                    }
+                    checked(x.div(&y), EvalErrorKind::DivisionByZero)
+                }
+                _ => Err(EvalErrorKind::TypeMismatch),
+            },
+            Operator::Mod => match (a, b) {
+                (Number(x), Number(y)) => {
+                    let zero = N::from_str("0").expect("\"0\" is always a valid numeric literal");
+                    if y == zero {
+                        return Err(EvalErrorKind::DivisionByZero);
+                    }
+                    checked(x.rem(&y), EvalErrorKind::DivisionByZero)
+                }
+                _ => Err(EvalErrorKind::TypeMismatch),
+            },
+            Operator::Mult => match (a, b) {
+                (Number(x), Number(y)) => checked(x.mul(&y), EvalErrorKind::InvalidOperation),
+                _ => Err(EvalErrorKind::TypeMismatch),
+            },
+            Operator::Exp => match (a, b) {
+                (Number(x), Number(y)) => {
+                    let ten = N::from_str("10").expect("\"10\" is always a valid numeric literal");
+                    checked(x.mul(&ten.pow(&y)), EvalErrorKind::InvalidOperation)
+                }
+                _ => Err(EvalErrorKind::TypeMismatch),
+            },
+            Operator::Pow => match (a, b) {
+                (Number(x), Number(y)) => checked(x.pow(&y), EvalErrorKind::InvalidOperation),
+                _ => Err(EvalErrorKind::TypeMismatch),
+            },
+            Operator::Lt => match (a, b) {
+                (Number(x), Number(y)) => Ok(Bool(x < y)),
+                _ => Err(EvalErrorKind::TypeMismatch),
+            },
+            Operator::Gt => match (a, b) {
+                (Number(x), Number(y)) => Ok(Bool(x > y)),
+                _ => Err(EvalErrorKind::TypeMismatch),
+            },
+            Operator::Le => match (a, b) {
+                (Number(x), Number(y)) => Ok(Bool(x <= y)),
+                _ => Err(EvalErrorKind::TypeMismatch),
+            },
+            Operator::Ge => match (a, b) {
+                (Number(x), Number(y)) => Ok(Bool(x >= y)),
+                _ => Err(EvalErrorKind::TypeMismatch),
+            },
+            Operator::Eq => match (a, b) {
+                (Number(x), Number(y)) => Ok(Bool(x == y)),
+                (Bool(x), Bool(y)) => Ok(Bool(x == y)),
+                _ => Err(EvalErrorKind::TypeMismatch),
+            },
+            Operator::Ne => match (a, b) {
+                (Number(x), Number(y)) => Ok(Bool(x != y)),
+                (Bool(x), Bool(y)) => Ok(Bool(x != y)),
+                _ => Err(EvalErrorKind::TypeMismatch),
+            },
+            Operator::And => match (a, b) {
+                (Bool(x), Bool(y)) => Ok(Bool(x && y)),
+                _ => Err(EvalErrorKind::TypeMismatch),
+            },
+            Operator::Or => match (a, b) {
+                (Bool(x), Bool(y)) => Ok(Bool(x || y)),
+                _ => Err(EvalErrorKind::TypeMismatch),
+            },
+            Operator::OpenParen
+            | Operator::CloseParen
+            | Operator::Assign
+            | Operator::Pos
+            | Operator::Neg => Err(EvalErrorKind::MalformedExpression),
+        }
+    }
+
+    /// Apply a unary `Pos`/`Neg` to a single operand. Reachable only for
+    /// those two variants; anything else is a `MalformedExpression` bug in
+    /// `tokenize`/`evaluate_with`.
+    fn operate_unary<N: Numeric>(&self, a: Value<N>) -> Result<Value<N>, EvalErrorKind> {
+        match (self, a) {
+            (Operator::Pos, Value::Number(x)) => Ok(Value::Number(x)),
+            (Operator::Neg, Value::Number(x)) => {
+                let zero = N::from_str("0").expect("\"0\" is always a valid numeric literal");
+                checked(zero.sub(&x), EvalErrorKind::InvalidOperation)
+            }
+            (Operator::Pos, Value::Bool(_)) | (Operator::Neg, Value::Bool(_)) => {
+                Err(EvalErrorKind::TypeMismatch)
+            }
+            _ => Err(EvalErrorKind::MalformedExpression),
         }
     }
 }
 
+/// Dispatch a builtin function call by name against already-resolved,
+/// already-typechecked numeric arguments (in source order).
+fn call_function<N: Numeric>(name: &str, args: &[N]) -> Result<N, EvalErrorKind> {
+    fn expect1<'a, N: Numeric>(name: &str, args: &'a [N]) -> Result<&'a N, EvalErrorKind> {
+        match args {
+            [a] => Ok(a),
+            _ => Err(EvalErrorKind::WrongArgumentCount {
+                function: name.to_string(),
+                expected: 1,
+                got: args.len(),
+            }),
+        }
+    }
+
+    match name {
+        "sqrt" => {
+            let x = expect1(name, args)?;
+            let half = N::from_str("0.5").expect("\"0.5\" is always a valid numeric literal");
+            Ok(x.pow(&half))
+        }
+        "abs" => {
+            let x = expect1(name, args)?;
+            let zero = N::from_str("0").expect("\"0\" is always a valid numeric literal");
+            Ok(if *x < zero { zero.sub(x) } else { x.clone() })
+        }
+        "sin" => Ok(expect1(name, args)?.sin()),
+        "cos" => Ok(expect1(name, args)?.cos()),
+        "tan" => Ok(expect1(name, args)?.tan()),
+        "ln" => Ok(expect1(name, args)?.ln()),
+        "floor" => Ok(expect1(name, args)?.floor()),
+        "ceil" => Ok(expect1(name, args)?.ceil()),
+        "log" => match args {
+            [x, base] => Ok(x.ln().div(&base.ln())),
+            _ => Err(EvalErrorKind::WrongArgumentCount {
+                function: "log".to_string(),
+                expected: 2,
+                got: args.len(),
+            }),
+        },
+        _ => Err(EvalErrorKind::UnknownFunction(name.to_string())),
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum Token {
-    Operator(Operator),
-    Operand(String),
+    /// An operator, and the character index (into the whitespace-stripped
+    /// expression) where it appeared.
+    Operator(Operator, usize),
+    /// An operand, and the character index of its first character.
+    Operand(String, usize),
+    /// A builtin function call: its name, how many comma-separated
+    /// arguments it was given, and the character index of the name's first
+    /// character.
+    Function(String, usize, usize),
+}
+
+/// An entry on `tokenize`'s operator stack: either a plain operator/paren,
+/// or the `(` of a function call (`sqrt(`), which also acts as a barrier
+/// for operator popping and tracks the in-progress argument count.
+enum StackEntry {
+    Op(Operator, usize),
+    FuncOpen {
+        name: String,
+        name_pos: usize,
+        arg_count: usize,
+    },
 }
 
 pub fn tokenize(expr: &str) -> Vec<Token> {
-    let mut operators: Vec<Operator> = Vec::new();
+    let mut operators: Vec<StackEntry> = Vec::new();
     let mut pe: Vec<Token> = Vec::new();
     // prepare expression
     let prepped_expr = format!("({})", expr.replace(" ", ""));
+    let chars: Vec<char> = prepped_expr.chars().collect();
+    // `prepped_expr` is wrapped in a synthetic pair of parens, so every
+    // index into it is one ahead of the corresponding index into the
+    // (whitespace-stripped) expression the caller passed in.
+    let pos_of = |i: usize| i.saturating_sub(1);
 
     let mut operand: Vec<char> = vec![];
-    for c in prepped_expr.chars() {
-        match <Operator as TryFrom<char>>::try_from(c) {
+    let mut operand_start = 0usize;
+    // Whether the next token is in operand position, e.g. right after `(`,
+    // right after another operator, or at the start of the expression. A
+    // `+`/`-` encountered here is unary (`Pos`/`Neg`) rather than binary.
+    let mut expect_operand = true;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+
+        // A comma separates arguments inside a function call; it isn't an
+        // `Operator` in its own right, so it's handled before the usual
+        // operator lexing.
+        if c == ',' {
+            if !operand.is_empty() {
+                pe.push(Token::Operand(
+                    String::from_iter(operand.to_owned()),
+                    pos_of(operand_start),
+                ));
+                operand.clear();
+            }
+            while let Some(top) = operators.last() {
+                match top {
+                    StackEntry::FuncOpen { .. } => break,
+                    StackEntry::Op(Operator::OpenParen, _) => break,
+                    StackEntry::Op(_, _) => {
+                        if let Some(StackEntry::Op(op, pos)) = operators.pop() {
+                            pe.push(Token::Operator(op, pos));
+                        }
+                    }
+                }
+            }
+            if let Some(StackEntry::FuncOpen { arg_count, .. }) = operators.last_mut() {
+                *arg_count += 1;
+            }
+            expect_operand = true;
+            i += 1;
+            continue;
+        }
+
+        let pair = if i + 1 < chars.len() {
+            Operator::try_from_pair(c, chars[i + 1])
+        } else {
+            None
+        };
+
+        let (op_result, consumed) = match pair {
+            Some(op) => (Ok(op), 2),
+            None => (<Operator as TryFrom<char>>::try_from(c), 1),
+        };
+        let op_result = op_result.map(|op| {
+            if !expect_operand {
+                return op;
+            }
+            match op {
+                Operator::Add => Operator::Pos,
+                Operator::Sub => Operator::Neg,
+                other => other,
+            }
+        });
+
+        match op_result {
+            // An identifier immediately followed by `(`, e.g. `sqrt(`, is a
+            // function call rather than a grouping paren around a bare
+            // variable reference.
+            Ok(Operator::OpenParen) if !operand.is_empty() => {
+                let name = String::from_iter(operand.to_owned());
+                let name_pos = pos_of(operand_start);
+                operand.clear();
+                operators.push(StackEntry::FuncOpen {
+                    name,
+                    name_pos,
+                    arg_count: 1,
+                });
+                expect_operand = true;
+                i += consumed;
+            }
             Ok(op) => {
                 if !operand.is_empty() {
-                    pe.push(Token::Operand(String::from_iter(operand.to_owned())));
+                    pe.push(Token::Operand(
+                        String::from_iter(operand.to_owned()),
+                        pos_of(operand_start),
+                    ));
                     operand.clear();
                 }
                 match op {
-                    Operator::OpenParen => operators.push(op),
+                    Operator::OpenParen => operators.push(StackEntry::Op(op, pos_of(i))),
+                    // A prefix unary `+`/`-` has nothing to its left to pop:
+                    // it starts a new (sub)expression rather than combining
+                    // with whatever operator is already on the stack, so
+                    // e.g. the `^` in `2^-3` must stay put until `-3` is
+                    // fully parsed.
+                    Operator::Pos | Operator::Neg => {
+                        operators.push(StackEntry::Op(op, pos_of(i)))
+                    }
                     Operator::CloseParen => {
-                        while let Some(nextop) = operators.pop() {
-                            match nextop {
-                                Operator::OpenParen => break,
-                                _ => pe.push(Token::Operator(nextop)),
+                        while let Some(top) = operators.pop() {
+                            match top {
+                                StackEntry::Op(Operator::OpenParen, _) => break,
+                                StackEntry::FuncOpen {
+                                    name,
+                                    name_pos,
+                                    arg_count,
+                                } => {
+                                    pe.push(Token::Function(name, arg_count, name_pos));
+                                    break;
+                                }
+                                StackEntry::Op(nextop, nextpos) => {
+                                    pe.push(Token::Operator(nextop, nextpos))
+                                }
                             }
                         }
                     }
                     _ => {
-                        while let Some(nextop) = operators.last() {
-                            if *nextop == Operator::OpenParen || op > *nextop {
+                        while let Some(top) = operators.last() {
+                            let nextop = match top {
+                                StackEntry::Op(nextop, _) => *nextop,
+                                StackEntry::FuncOpen { .. } => break,
+                            };
+                            if nextop == Operator::OpenParen {
+                                break;
+                            }
+                            let should_pop = match op.associativity() {
+                                Associativity::Right => nextop > op,
+                                Associativity::Left => nextop >= op,
+                            };
+                            if !should_pop {
                                 break;
                             }
-                            pe.push(Token::Operator(operators.pop().unwrap()));
+                            if let Some(StackEntry::Op(popped, popped_pos)) = operators.pop() {
+                                pe.push(Token::Operator(popped, popped_pos));
+                            }
                         }
 
-                        operators.push(op)
+                        operators.push(StackEntry::Op(op, pos_of(i)))
                     }
                 }
+                expect_operand = !matches!(op, Operator::CloseParen);
+                i += consumed;
             }
             Err(_) => {
-                operand.push(c) // not an operator so must be operand
+                if operand.is_empty() {
+                    operand_start = i;
+                }
+                operand.push(c); // not an operator so must be operand
+                expect_operand = false;
+                i += 1;
             }
         }
     }
@@ -176,37 +945,163 @@ pub fn tokenize(expr: &str) -> Vec<Token> {
     return pe;
 }
 
-pub fn evaluate(tokens: Vec<Token>) -> Result<f64, PostfixEvalError> {
-    let mut operands: Vec<f64> = vec![];
+/// An operand that hasn't been resolved to a `Value` yet: either a literal
+/// already parsed, or the name of a variable that must be looked up (or, for
+/// `Assign`, bound) against an `Environment`.
+enum StackItem<N: Numeric> {
+    Value(Value<N>),
+    /// A variable name together with the position it was referenced at, so
+    /// an unresolved lookup can still report where it went wrong.
+    Name(String, usize),
+}
+
+impl<N: Numeric> StackItem<N> {
+    fn resolve(self, env: &Environment<N>) -> Result<Value<N>, EvalError> {
+        match self {
+            StackItem::Value(v) => Ok(v),
+            StackItem::Name(name, pos) => {
+                if let Some(v) = env.get(&name) {
+                    return Ok(v.clone());
+                }
+                // `pi`/`e` are constant operands, resolved the same way a
+                // variable would be but only once the environment doesn't
+                // already bind (and so shadow) that name.
+                match name.as_str() {
+                    "pi" => Ok(Value::Number(N::pi())),
+                    "e" => Ok(Value::Number(N::e())),
+                    _ => Err(EvalError {
+                        kind: EvalErrorKind::UndefinedVariable(name),
+                        pos,
+                    }),
+                }
+            }
+        }
+    }
+}
+
+/// Evaluate `tokens` with no variables in scope, against the `N` number
+/// backend (e.g. `f64` or `Rational`).
+pub fn evaluate<N: Numeric>(tokens: Vec<Token>) -> Result<Value<N>, EvalError> {
+    evaluate_with(tokens, &mut Environment::new())
+}
+
+/// Evaluate `tokens` against a shared `Environment`, so that identifiers
+/// resolve to previously assigned variables and an `Assign` token binds its
+/// left-hand name rather than erroring as an undefined variable.
+pub fn evaluate_with<N: Numeric>(
+    tokens: Vec<Token>,
+    env: &mut Environment<N>,
+) -> Result<Value<N>, EvalError> {
+    let mut operands: Vec<StackItem<N>> = vec![];
 
     for token in tokens {
         match token {
-            Token::Operand(op) => match op.parse::<f64>() {
-                Ok(f) => operands.push(f),
-                Err(_) => return Err(PostfixEvalError),
+            Token::Operand(op, pos) => match N::from_str(&op) {
+                Some(n) => operands.push(StackItem::Value(Value::Number(n))),
+                None => {
+                    let looks_numeric = op
+                        .chars()
+                        .next()
+                        .is_some_and(|c| c.is_ascii_digit() || c == '.');
+                    if looks_numeric {
+                        return Err(EvalError {
+                            kind: EvalErrorKind::InvalidNumber(op),
+                            pos,
+                        });
+                    }
+                    operands.push(StackItem::Name(op, pos));
+                }
             },
 
-            Token::Operator(op) => {
+            Token::Operator(Operator::Assign, pos) => {
+                if operands.len() < 2 {
+                    return Err(EvalError {
+                        kind: EvalErrorKind::MissingOperand,
+                        pos,
+                    });
+                }
+                let rhs = operands.pop().unwrap();
+                let lhs = operands.pop().unwrap();
+                let name = match lhs {
+                    StackItem::Name(name, _) => name,
+                    StackItem::Value(_) => {
+                        return Err(EvalError {
+                            kind: EvalErrorKind::InvalidAssignmentTarget,
+                            pos,
+                        })
+                    }
+                };
+                let value = rhs.resolve(env)?;
+                env.insert(name, value.clone());
+                operands.push(StackItem::Value(value));
+            }
+
+            Token::Operator(op @ (Operator::Pos | Operator::Neg), pos) => {
+                let a = operands
+                    .pop()
+                    .ok_or(EvalError {
+                        kind: EvalErrorKind::MissingOperand,
+                        pos,
+                    })?
+                    .resolve(env)?;
+
+                let val = op.operate_unary(a).map_err(|kind| EvalError { kind, pos })?;
+                operands.push(StackItem::Value(val));
+            }
+
+            Token::Operator(op, pos) => {
                 if operands.len() < 2 {
-                    return Err(PostfixEvalError);
+                    return Err(EvalError {
+                        kind: EvalErrorKind::MissingOperand,
+                        pos,
+                    });
                 }
-                let b = operands.pop().unwrap();
-                let a = operands.pop().unwrap();
+                let b = operands.pop().unwrap().resolve(env)?;
+                let a = operands.pop().unwrap().resolve(env)?;
 
-                let val = op.operate(a, b);
-                if val.is_nan() {
-                    return Err(PostfixEvalError);
+                let val = op.operate(a, b).map_err(|kind| EvalError { kind, pos })?;
+                operands.push(StackItem::Value(val));
+            }
+
+            Token::Function(name, arity, pos) => {
+                if operands.len() < arity {
+                    return Err(EvalError {
+                        kind: EvalErrorKind::MissingOperand,
+                        pos,
+                    });
+                }
+                let mut args: Vec<N> = Vec::with_capacity(arity);
+                for _ in 0..arity {
+                    match operands.pop().unwrap().resolve(env)? {
+                        Value::Number(n) => args.push(n),
+                        Value::Bool(_) => {
+                            return Err(EvalError {
+                                kind: EvalErrorKind::TypeMismatch,
+                                pos,
+                            })
+                        }
+                    }
                 }
-                operands.push(val);
+                args.reverse(); // popped back-to-front; restore source order
+
+                let result =
+                    call_function(&name, &args).map_err(|kind| EvalError { kind, pos })?;
+                operands.push(StackItem::Value(Value::Number(result)));
             }
         }
     }
 
-    if operands.len() != 1 {
-        return Err(PostfixEvalError);
+    match operands.len() {
+        1 => operands.pop().unwrap().resolve(env),
+        0 => Err(EvalError {
+            kind: EvalErrorKind::MissingOperand,
+            pos: 0,
+        }),
+        n => Err(EvalError {
+            kind: EvalErrorKind::TrailingOperands(n - 1),
+            pos: 0,
+        }),
     }
-
-    Ok(operands[0])
 }
 
 #[cfg(test)]
@@ -222,18 +1117,26 @@ mod tests {
         assert_eq!(Operator::try_from('('), Ok(Operator::OpenParen));
         assert_eq!(Operator::try_from('^'), Ok(Operator::Pow));
         assert_eq!(Operator::try_from('-'), Ok(Operator::Sub));
+        assert_eq!(Operator::try_from('<'), Ok(Operator::Lt));
+        assert_eq!(Operator::try_from('>'), Ok(Operator::Gt));
         assert!(Operator::try_from('a').is_err());
     }
 
     #[test]
-    fn test_operator_into_char() {
-        assert_eq!(char::from(Operator::Add), '+');
-        assert_eq!(char::from(Operator::CloseParen), ')');
-        assert_eq!(char::from(Operator::Div), '/');
-        assert_eq!(char::from(Operator::Mult), '*');
-        assert_eq!(char::from(Operator::OpenParen), '(');
-        assert_eq!(char::from(Operator::Pow), '^');
-        assert_eq!(char::from(Operator::Sub), '-');
+    fn test_operator_symbol() {
+        assert_eq!(Operator::Add.symbol(), "+");
+        assert_eq!(Operator::CloseParen.symbol(), ")");
+        assert_eq!(Operator::Div.symbol(), "/");
+        assert_eq!(Operator::Mult.symbol(), "*");
+        assert_eq!(Operator::OpenParen.symbol(), "(");
+        assert_eq!(Operator::Pow.symbol(), "^");
+        assert_eq!(Operator::Sub.symbol(), "-");
+        assert_eq!(Operator::Le.symbol(), "<=");
+        assert_eq!(Operator::Ge.symbol(), ">=");
+        assert_eq!(Operator::Eq.symbol(), "==");
+        assert_eq!(Operator::Ne.symbol(), "!=");
+        assert_eq!(Operator::And.symbol(), "&&");
+        assert_eq!(Operator::Or.symbol(), "||");
     }
 
     #[test]
@@ -243,6 +1146,10 @@ mod tests {
         assert!(Operator::Div > Operator::Sub);
         assert!(Operator::Add == Operator::Sub);
         assert!(Operator::Mult == Operator::Div);
+        assert!(Operator::Add > Operator::Lt);
+        assert!(Operator::Lt > Operator::And);
+        assert!(Operator::And > Operator::Or);
+        assert!(Operator::Eq == Operator::Lt);
     }
 
     #[test]
@@ -253,198 +1160,400 @@ mod tests {
         assert_eq!(
             tokenize("a+b"),
             vec![
-                Operand("a".to_string()),
-                Operand("b".to_string()),
-                Operator(Add),
+                Operand("a".to_string(), 0),
+                Operand("b".to_string(), 2),
+                Operator(Add, 1),
             ]
         );
 
         assert_eq!(
             tokenize("a+b*c"),
             vec![
-                Operand("a".to_string()),
-                Operand("b".to_string()),
-                Operand("c".to_string()),
-                Operator(Mult),
-                Operator(Add),
+                Operand("a".to_string(), 0),
+                Operand("b".to_string(), 2),
+                Operand("c".to_string(), 4),
+                Operator(Mult, 3),
+                Operator(Add, 1),
             ]
         );
 
         assert_eq!(
             tokenize("(a+b)*c"),
             vec![
-                Operand("a".to_string()),
-                Operand("b".to_string()),
-                Operator(Add),
-                Operand("c".to_string()),
-                Operator(Mult),
+                Operand("a".to_string(), 1),
+                Operand("b".to_string(), 3),
+                Operator(Add, 2),
+                Operand("c".to_string(), 6),
+                Operator(Mult, 5),
             ]
         );
 
         assert_eq!(
             tokenize("a+b*c-d/e^f"),
             vec![
-                Operand("a".to_string()),
-                Operand("b".to_string()),
-                Operand("c".to_string()),
-                Operator(Mult),
-                Operator(Add),
-                Operand("d".to_string()),
-                Operand("e".to_string()),
-                Operand("f".to_string()),
-                Operator(Pow),
-                Operator(Div),
-                Operator(Sub),
+                Operand("a".to_string(), 0),
+                Operand("b".to_string(), 2),
+                Operand("c".to_string(), 4),
+                Operator(Mult, 3),
+                Operator(Add, 1),
+                Operand("d".to_string(), 6),
+                Operand("e".to_string(), 8),
+                Operand("f".to_string(), 10),
+                Operator(Pow, 9),
+                Operator(Div, 7),
+                Operator(Sub, 5),
             ]
         );
 
         assert_eq!(
             tokenize("a.3+b.2*c.1-d/e^f"),
             vec![
-                Operand("a.3".to_string()),
-                Operand("b.2".to_string()),
-                Operand("c.1".to_string()),
-                Operator(Mult),
-                Operator(Add),
-                Operand("d".to_string()),
-                Operand("e".to_string()),
-                Operand("f".to_string()),
-                Operator(Pow),
-                Operator(Div),
-                Operator(Sub),
+                Operand("a.3".to_string(), 0),
+                Operand("b.2".to_string(), 4),
+                Operand("c.1".to_string(), 8),
+                Operator(Mult, 7),
+                Operator(Add, 3),
+                Operand("d".to_string(), 12),
+                Operand("e".to_string(), 14),
+                Operand("f".to_string(), 16),
+                Operator(Pow, 15),
+                Operator(Div, 13),
+                Operator(Sub, 11),
             ]
         );
 
         assert_eq!(
             tokenize("a^(1/2)"),
             vec![
-                Operand("a".to_string()),
-                Operand("1".to_string()),
-                Operand("2".to_string()),
-                Operator(Div),
-                Operator(Pow),
+                Operand("a".to_string(), 0),
+                Operand("1".to_string(), 3),
+                Operand("2".to_string(), 5),
+                Operator(Div, 4),
+                Operator(Pow, 1),
             ]
         );
 
         assert_eq!(
             tokenize("aE10*2"),
             vec![
-                Operand("a".to_string()),
-                Operand("10".to_string()),
-                Operator(Exp),
-                Operand("2".to_string()),
-                Operator(Mult),
+                Operand("a".to_string(), 0),
+                Operand("10".to_string(), 2),
+                Operator(Exp, 1),
+                Operand("2".to_string(), 5),
+                Operator(Mult, 4),
             ]
         );
 
         assert_eq!(
             tokenize("a%2 + 3"),
             vec![
-                Operand("a".to_string()),
-                Operand("2".to_string()),
-                Operator(Mod),
-                Operand("3".to_string()),
-                Operator(Add),
+                Operand("a".to_string(), 0),
+                Operand("2".to_string(), 2),
+                Operator(Mod, 1),
+                Operand("3".to_string(), 4),
+                Operator(Add, 3),
             ]
         );
     }
 
     #[test]
-    fn test_evaluate_simple() {
+    fn test_tokenize_comparison_and_logical() {
         use self::Operator::*;
         use Token::*;
 
-        // Test basic arithmetic
         assert_eq!(
-            evaluate(vec![
-                Operand("3".to_string()),
-                Operand("4".to_string()),
-                Operator(Add)
-            ]),
-            Ok(7.0)
+            tokenize("a<=b"),
+            vec![
+                Operand("a".to_string(), 0),
+                Operand("b".to_string(), 3),
+                Operator(Le, 1),
+            ]
         );
 
         assert_eq!(
-            evaluate(vec![
-                Operand("10".to_string()),
-                Operand("5".to_string()),
-                Operator(Sub)
-            ]),
-            Ok(5.0)
+            tokenize("a&&b||c"),
+            vec![
+                Operand("a".to_string(), 0),
+                Operand("b".to_string(), 3),
+                Operator(And, 1),
+                Operand("c".to_string(), 6),
+                Operator(Or, 4),
+            ]
         );
 
         assert_eq!(
-            evaluate(vec![
-                Operand("6".to_string()),
-                Operand("3".to_string()),
-                Operator(Mult)
-            ]),
-            Ok(18.0)
+            tokenize("a>0&&b<1"),
+            vec![
+                Operand("a".to_string(), 0),
+                Operand("0".to_string(), 2),
+                Operator(Gt, 1),
+                Operand("b".to_string(), 5),
+                Operand("1".to_string(), 7),
+                Operator(Lt, 6),
+                Operator(And, 3),
+            ]
         );
+    }
+
+    #[test]
+    fn test_tokenize_assignment_positions() {
+        use self::Operator::*;
+        use Token::*;
 
         assert_eq!(
-            evaluate(vec![
-                Operand("15".to_string()),
-                Operand("3".to_string()),
-                Operator(Div)
-            ]),
-            Ok(5.0)
+            tokenize("x=y=3"),
+            vec![
+                Operand("x".to_string(), 0),
+                Operand("y".to_string(), 2),
+                Operand("3".to_string(), 4),
+                Operator(Assign, 3),
+                Operator(Assign, 1),
+            ]
         );
     }
 
     #[test]
-    fn test_evaluate_complex() {
+    fn test_tokenize_unary_operators() {
         use self::Operator::*;
         use Token::*;
-        // Test more complex expressions
+
         assert_eq!(
-            evaluate(vec![
-                Operand("3".to_string()),
-                Operand("4".to_string()),
-                Operand("2".to_string()),
-                Operator(Mult),
-                Operator(Add)
-            ]),
-            Ok(11.0)
+            tokenize("-3+4"),
+            vec![
+                Operand("3".to_string(), 1),
+                Operator(Neg, 0),
+                Operand("4".to_string(), 3),
+                Operator(Add, 2),
+            ]
         );
 
         assert_eq!(
-            evaluate(vec![
-                Operand("10".to_string()),
-                Operand("2".to_string()),
-                Operator(Pow)
-            ]),
-            Ok(100.0)
+            tokenize("2^-3"),
+            vec![
+                Operand("2".to_string(), 0),
+                Operand("3".to_string(), 3),
+                Operator(Neg, 2),
+                Operator(Pow, 1),
+            ]
         );
+    }
+
+    #[test]
+    fn test_evaluate_simple() {
+        // Test basic arithmetic
+        assert_eq!(evaluate(tokenize("3+4")), Ok(Value::Number(7.0)));
+        assert_eq!(evaluate(tokenize("10-5")), Ok(Value::Number(5.0)));
+        assert_eq!(evaluate(tokenize("6*3")), Ok(Value::Number(18.0)));
+        assert_eq!(evaluate(tokenize("15/3")), Ok(Value::Number(5.0)));
+    }
+
+    #[test]
+    fn test_evaluate_complex() {
+        // Test more complex expressions
+        assert_eq!(evaluate(tokenize("3+4*2")), Ok(Value::Number(11.0)));
+        assert_eq!(evaluate(tokenize("10^2")), Ok(Value::Number(100.0)));
 
         // Test decimal numbers
+        assert_eq!(evaluate(tokenize("3.5+2.5")), Ok(Value::Number(6.0)));
+
+        // Test modulo
+        assert_eq!(evaluate(tokenize("10%3")), Ok(Value::Number(1.0)));
+
+        // Test exponentiation
+        assert_eq!(evaluate(tokenize("2E3")), Ok(Value::Number(2000.0)));
+    }
+
+    #[test]
+    fn test_evaluate_unary_operators() {
+        assert_eq!(evaluate(tokenize("-3+4")), Ok(Value::Number(1.0)));
+        assert_eq!(evaluate(tokenize("--3")), Ok(Value::Number(3.0)));
+        assert_eq!(evaluate(tokenize("2*-3")), Ok(Value::Number(-6.0)));
+    }
+
+    #[test]
+    fn test_evaluate_pow_is_right_associative() {
+        // 2^(3^2) = 2^9 = 512, not (2^3)^2 = 64.
+        assert_eq!(evaluate(tokenize("2^3^2")), Ok(Value::Number(512.0)));
+    }
+
+    #[test]
+    fn test_evaluate_unary_minus_binds_looser_than_pow() {
+        // -3^2 = -(3^2) = -9, not (-3)^2 = 9.
+        assert_eq!(evaluate(tokenize("-3^2")), Ok(Value::Number(-9.0)));
+        assert_eq!(evaluate(tokenize("-2^2")), Ok(Value::Number(-4.0)));
+    }
+
+    #[test]
+    fn test_evaluate_negative_exponent() {
+        // A prefix unary operator must not pop `^` off the stack before its
+        // own operand is parsed, or `2^-3` comes out as the malformed
+        // postfix `2 3 Pow Neg`.
+        assert_eq!(evaluate(tokenize("2^-3")), Ok(Value::Number(0.125)));
+        assert_eq!(evaluate(tokenize("2^-2")), Ok(Value::Number(0.25)));
+        assert_eq!(evaluate(tokenize("4^-1")), Ok(Value::Number(0.25)));
+        assert_eq!(evaluate(tokenize("2^-3+1")), Ok(Value::Number(1.125)));
+    }
+
+    #[test]
+    fn test_tokenize_function_call() {
+        use self::Operator::*;
+        use Token::*;
+
         assert_eq!(
-            evaluate(vec![
-                Operand("3.5".to_string()),
-                Operand("2.5".to_string()),
-                Operator(Add)
-            ]),
-            Ok(6.0)
+            tokenize("sqrt(4)"),
+            vec![Operand("4".to_string(), 5), Function("sqrt".to_string(), 1, 0)]
         );
 
-        // Test modulo
         assert_eq!(
-            evaluate(vec![
-                Operand("10".to_string()),
-                Operand("3".to_string()),
-                Operator(Mod)
-            ]),
-            Ok(1.0)
+            tokenize("log(8,2)"),
+            vec![
+                Operand("8".to_string(), 4),
+                Operand("2".to_string(), 6),
+                Function("log".to_string(), 2, 0),
+            ]
         );
 
-        // Test exponentiation
+        // A function call's arguments are still fully-fledged expressions.
+        assert_eq!(
+            tokenize("sqrt(2+2)"),
+            vec![
+                Operand("2".to_string(), 5),
+                Operand("2".to_string(), 7),
+                Operator(Add, 6),
+                Function("sqrt".to_string(), 1, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_evaluate_builtin_functions() {
+        assert_eq!(evaluate(tokenize("sqrt(4)")), Ok(Value::Number(2.0)));
+        assert_eq!(evaluate(tokenize("abs(-3)")), Ok(Value::Number(3.0)));
+        assert_eq!(evaluate(tokenize("floor(3.7)")), Ok(Value::Number(3.0)));
+        assert_eq!(evaluate(tokenize("ceil(3.2)")), Ok(Value::Number(4.0)));
+    }
+
+    #[test]
+    fn test_evaluate_multi_argument_function() {
+        assert_eq!(evaluate(tokenize("log(8,2)")), Ok(Value::Number(3.0)));
+    }
+
+    #[test]
+    fn test_evaluate_constants() {
+        assert_eq!(
+            evaluate::<f64>(tokenize("pi")),
+            Ok(Value::Number(std::f64::consts::PI))
+        );
+        assert_eq!(
+            evaluate::<f64>(tokenize("sin(pi/2)")),
+            Ok(Value::Number(1.0))
+        );
+    }
+
+    #[test]
+    fn test_evaluate_unknown_function() {
+        assert_eq!(
+            evaluate::<f64>(tokenize("frobnicate(1)")).map_err(|e| e.kind),
+            Err(EvalErrorKind::UnknownFunction("frobnicate".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_evaluate_wrong_argument_count() {
+        assert_eq!(
+            evaluate::<f64>(tokenize("sqrt(1,2)")).map_err(|e| e.kind),
+            Err(EvalErrorKind::WrongArgumentCount {
+                function: "sqrt".to_string(),
+                expected: 1,
+                got: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn test_evaluate_comparison_and_logical() {
+        assert_eq!(
+            evaluate::<f64>(tokenize("3<4")),
+            Ok(Value::Bool(true))
+        );
+
+        assert_eq!(
+            evaluate::<f64>(tokenize("3<4&&1==1")),
+            Ok(Value::Bool(true))
+        );
+    }
+
+    #[test]
+    fn test_evaluate_type_mismatch() {
+        // Comparing a bool (the result of `3<4`) with a number is a type
+        // error, not a silently coerced comparison.
+        assert_eq!(
+            evaluate::<f64>(tokenize("3<4<5")).map_err(|e| e.kind),
+            Err(EvalErrorKind::TypeMismatch)
+        );
+    }
+
+    #[test]
+    fn test_evaluate_division_by_zero() {
+        // A zero divisor is rejected explicitly, rather than producing a
+        // float `inf` (which `checked`'s NaN check would let through) or
+        // `NaN`.
+        assert_eq!(
+            evaluate::<f64>(tokenize("5/0")).map_err(|e| e.kind),
+            Err(EvalErrorKind::DivisionByZero)
+        );
+        assert_eq!(
+            evaluate::<f64>(tokenize("5%0")).map_err(|e| e.kind),
+            Err(EvalErrorKind::DivisionByZero)
+        );
+    }
+
+    #[test]
+    fn test_evaluate_with_variables() {
+        let mut env = Environment::new();
+        env.insert("a".to_string(), Value::Number(5.0));
+
         assert_eq!(
-            evaluate(vec![
-                Operand("2".to_string()),
-                Operand("3".to_string()),
-                Operator(Exp)
-            ]),
-            Ok(2000.0)
+            evaluate_with(tokenize("a+2"), &mut env),
+            Ok(Value::Number(7.0))
+        );
+    }
+
+    #[test]
+    fn test_evaluate_with_assignment() {
+        let mut env = Environment::new();
+
+        assert_eq!(
+            evaluate_with(tokenize("x=3+4"), &mut env),
+            Ok(Value::Number(7.0))
+        );
+        assert_eq!(env.get("x"), Some(&Value::Number(7.0)));
+
+        // A later statement can read the variable back out of the shared
+        // environment.
+        assert_eq!(
+            evaluate_with(tokenize("x*2"), &mut env),
+            Ok(Value::Number(14.0))
+        );
+    }
+
+    #[test]
+    fn test_evaluate_with_chained_assignment_is_right_associative() {
+        let mut env = Environment::new();
+
+        assert_eq!(
+            evaluate_with(tokenize("x=y=3"), &mut env),
+            Ok(Value::Number(3.0))
+        );
+        assert_eq!(env.get("x"), Some(&Value::Number(3.0)));
+        assert_eq!(env.get("y"), Some(&Value::Number(3.0)));
+    }
+
+    #[test]
+    fn test_evaluate_undefined_variable() {
+        assert_eq!(
+            evaluate::<f64>(tokenize("a+1")).map_err(|e| e.kind),
+            Err(EvalErrorKind::UndefinedVariable("a".to_string()))
         );
     }
 
@@ -454,18 +1563,73 @@ mod tests {
         use Token::*;
 
         // Test error case: invalid number
-        assert!(evaluate(vec![Operand("not_a_number".to_string())]).is_err());
+        assert_eq!(
+            evaluate::<f64>(vec![Operand("not_a_number".to_string(), 0)]).map_err(|e| e.kind),
+            Err(EvalErrorKind::UndefinedVariable("not_a_number".to_string()))
+        );
 
         // Test error case: insufficient operands
-        assert!(evaluate(vec![Operand("5".to_string()), Operator(Add)]).is_err());
+        assert_eq!(
+            evaluate::<f64>(vec![Operand("5".to_string(), 0), Operator(Add, 1)])
+                .map_err(|e| e.kind),
+            Err(EvalErrorKind::MissingOperand)
+        );
 
         // Test error case: too many operands
-        assert!(evaluate(vec![
-            Operand("5".to_string()),
-            Operand("3".to_string()),
-            Operand("2".to_string()),
-            Operator(Add)
-        ])
-        .is_err());
+        assert_eq!(
+            evaluate::<f64>(vec![
+                Operand("5".to_string(), 0),
+                Operand("3".to_string(), 1),
+                Operand("2".to_string(), 2),
+                Operator(Add, 3)
+            ])
+            .map_err(|e| e.kind),
+            Err(EvalErrorKind::TrailingOperands(1))
+        );
+    }
+
+    #[test]
+    fn test_rational_exact_decimal_addition() {
+        let a = Rational::from_str("0.1").unwrap();
+        let b = Rational::from_str("0.2").unwrap();
+        assert_eq!(a.add(&b), Rational::from_str("0.3").unwrap());
+    }
+
+    #[test]
+    fn test_rational_reduces_fractions() {
+        // 1/2 + 1/2 should reduce to the whole number 1, not 2/2.
+        let half = Rational::new(BigInt::from(1), BigInt::from(2));
+        let whole = half.add(&half);
+        assert_eq!(whole, Rational::new(BigInt::from(1), BigInt::from(1)));
+        assert_eq!(whole.to_string(), "1");
+    }
+
+    #[test]
+    fn test_rational_remainder() {
+        let ten = Rational::from_str("10").unwrap();
+        let three = Rational::from_str("3").unwrap();
+        assert_eq!(ten.rem(&three), Rational::from_str("1").unwrap());
+    }
+
+    #[test]
+    fn test_rational_leading_dot_literal() {
+        // ".5" has an empty integer part, which should parse as 0.5 rather
+        // than fail, just as the float backend's ".5".parse::<f64>() does.
+        assert_eq!(
+            Rational::from_str(".5").unwrap(),
+            Rational::new(BigInt::from(1), BigInt::from(2))
+        );
+        assert_eq!(
+            Rational::from_str("-.5").unwrap(),
+            Rational::new(BigInt::from(-1), BigInt::from(2))
+        );
+    }
+
+    #[test]
+    fn test_evaluate_with_rational_backend() {
+        assert_eq!(
+            evaluate::<Rational>(tokenize("0.1+0.2")),
+            Ok(Value::Number(Rational::from_str("0.3").unwrap()))
+        );
     }
 }